@@ -0,0 +1,597 @@
+//! Lowers a compiled decoder down to a BPF program for the kernel's
+//! `BPF_PROG_TYPE_LIRC_MODE2` hook, so a single [`Irp`] can drive both
+//! userspace decoding (via [`NFA::decoder`]/[`DFA::decoder`]) and in-kernel
+//! decoding in `rc-core` from the same source.
+//!
+//! This module is only compiled with the `bpf` feature enabled, since it has
+//! nothing to do with userspace decoding and most users of this crate never
+//! touch the kernel.
+
+use crate::{Irp, DFA};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Registers of the classic eBPF virtual machine.
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+enum Reg {
+    R0,
+    R1,
+    R2,
+    R3,
+    R4,
+    R6,
+    R7,
+    R8,
+    R9,
+    R10,
+}
+
+impl Reg {
+    fn id(self) -> u8 {
+        match self {
+            Reg::R0 => 0,
+            Reg::R1 => 1,
+            Reg::R2 => 2,
+            Reg::R3 => 3,
+            Reg::R4 => 4,
+            Reg::R6 => 6,
+            Reg::R7 => 7,
+            Reg::R8 => 8,
+            Reg::R9 => 9,
+            Reg::R10 => 10,
+        }
+    }
+}
+
+// Opcodes used by the instructions this lowering emits. These match the
+// classic eBPF ISA (see linux/bpf.h / Documentation/bpf/instruction-set.rst).
+const BPF_MOV64_IMM: u8 = 0xb7;
+const BPF_MOV64_REG: u8 = 0xbf;
+const BPF_ADD64_IMM: u8 = 0x07;
+const BPF_AND64_IMM: u8 = 0x57;
+const BPF_OR64_REG: u8 = 0x4f;
+const BPF_LSH64_IMM: u8 = 0x67;
+const BPF_RSH64_IMM: u8 = 0x77;
+const BPF_JEQ_IMM: u8 = 0x15;
+const BPF_JNE_IMM: u8 = 0x55;
+const BPF_JGE_IMM: u8 = 0x35;
+const BPF_JLT_IMM: u8 = 0xa5;
+const BPF_JA: u8 = 0x05;
+const BPF_CALL: u8 = 0x85;
+const BPF_EXIT: u8 = 0x95;
+const BPF_ST_W: u8 = 0x62;
+const BPF_STX_W: u8 = 0x63;
+const BPF_LDX_W: u8 = 0x61;
+const BPF_LD_IMM64: u8 = 0x18;
+
+/// `src_reg` value on a `BPF_LD_IMM64` that marks the immediate as a file descriptor the
+/// kernel should resolve to a map pointer at load time, rather than a plain 64-bit constant.
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+/// BPF helper function IDs used by this lowering, in the kernel's stable numbering.
+/// `rc_keydown` is the scancode-reporting helper exposed to `BPF_PROG_TYPE_LIRC_MODE2` programs.
+const BPF_FUNC_MAP_LOOKUP_ELEM: i32 = 1;
+const BPF_FUNC_RC_KEYDOWN: i32 = 78;
+
+#[derive(Debug, Clone, Copy)]
+struct Insn {
+    opcode: u8,
+    dst: u8,
+    src: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl Insn {
+    fn to_bytes(self) -> [u8; 8] {
+        let mut b = [0u8; 8];
+        b[0] = self.opcode;
+        b[1] = (self.dst & 0x0f) | ((self.src & 0x0f) << 4);
+        b[2..4].copy_from_slice(&self.off.to_le_bytes());
+        b[4..8].copy_from_slice(&self.imm.to_le_bytes());
+        b
+    }
+}
+
+fn mov_imm(dst: Reg, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_MOV64_IMM,
+        dst: dst.id(),
+        src: 0,
+        off: 0,
+        imm,
+    }
+}
+
+fn mov_reg(dst: Reg, src: Reg) -> Insn {
+    Insn {
+        opcode: BPF_MOV64_REG,
+        dst: dst.id(),
+        src: src.id(),
+        off: 0,
+        imm: 0,
+    }
+}
+
+fn add_imm(dst: Reg, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_ADD64_IMM,
+        dst: dst.id(),
+        src: 0,
+        off: 0,
+        imm,
+    }
+}
+
+fn and_imm(dst: Reg, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_AND64_IMM,
+        dst: dst.id(),
+        src: 0,
+        off: 0,
+        imm,
+    }
+}
+
+fn rsh_imm(dst: Reg, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_RSH64_IMM,
+        dst: dst.id(),
+        src: 0,
+        off: 0,
+        imm,
+    }
+}
+
+fn lsh_imm(dst: Reg, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_LSH64_IMM,
+        dst: dst.id(),
+        src: 0,
+        off: 0,
+        imm,
+    }
+}
+
+fn or_reg(dst: Reg, src: Reg) -> Insn {
+    Insn {
+        opcode: BPF_OR64_REG,
+        dst: dst.id(),
+        src: src.id(),
+        off: 0,
+        imm: 0,
+    }
+}
+
+fn jeq_imm(src: Reg, imm: i32, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_JEQ_IMM,
+        dst: src.id(),
+        src: 0,
+        off,
+        imm,
+    }
+}
+
+fn jne_imm(src: Reg, imm: i32, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_JNE_IMM,
+        dst: src.id(),
+        src: 0,
+        off,
+        imm,
+    }
+}
+
+fn jge_imm(src: Reg, imm: i32, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_JGE_IMM,
+        dst: src.id(),
+        src: 0,
+        off,
+        imm,
+    }
+}
+
+fn jlt_imm(src: Reg, imm: i32, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_JLT_IMM,
+        dst: src.id(),
+        src: 0,
+        off,
+        imm,
+    }
+}
+
+fn ja(off: i16) -> Insn {
+    Insn {
+        opcode: BPF_JA,
+        dst: 0,
+        src: 0,
+        off,
+        imm: 0,
+    }
+}
+
+fn call(helper: i32) -> Insn {
+    Insn {
+        opcode: BPF_CALL,
+        dst: 0,
+        src: 0,
+        off: 0,
+        imm: helper,
+    }
+}
+
+fn exit() -> Insn {
+    Insn {
+        opcode: BPF_EXIT,
+        dst: 0,
+        src: 0,
+        off: 0,
+        imm: 0,
+    }
+}
+
+fn st_w(dst: Reg, off: i16, imm: i32) -> Insn {
+    Insn {
+        opcode: BPF_ST_W,
+        dst: dst.id(),
+        src: 0,
+        off,
+        imm,
+    }
+}
+
+fn stx_w(dst: Reg, src: Reg, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_STX_W,
+        dst: dst.id(),
+        src: src.id(),
+        off,
+        imm: 0,
+    }
+}
+
+fn ldx_w(dst: Reg, src: Reg, off: i16) -> Insn {
+    Insn {
+        opcode: BPF_LDX_W,
+        dst: dst.id(),
+        src: src.id(),
+        off,
+        imm: 0,
+    }
+}
+
+/// A `BPF_LD_IMM64` pseudo-load of a map file descriptor. Takes two instruction slots: the
+/// first carries the fd as its immediate with `src_reg` set to [`BPF_PSEUDO_MAP_FD`], which
+/// tells the verifier to turn it into a pointer to the map at load time; the second slot
+/// carries the (unused) high 32 bits.
+fn ld_map_fd(dst: Reg, map_fd: i32) -> [Insn; 2] {
+    [
+        Insn {
+            opcode: BPF_LD_IMM64,
+            dst: dst.id(),
+            src: BPF_PSEUDO_MAP_FD,
+            off: 0,
+            imm: map_fd,
+        },
+        Insn {
+            opcode: 0,
+            dst: 0,
+            src: 0,
+            off: 0,
+            imm: 0,
+        },
+    ]
+}
+
+/// Something went wrong lowering the decoder to BPF.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BpfError {
+    /// The IRP could not be compiled to a decoder at all.
+    Compile(String),
+    /// The decoder has more states than this lowering can address.
+    TooManyStates,
+}
+
+impl fmt::Display for BpfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BpfError::Compile(msg) => write!(f, "could not compile irp: {msg}"),
+            BpfError::TooManyStates => write!(f, "decoder has too many states for bpf"),
+        }
+    }
+}
+
+impl std::error::Error for BpfError {}
+
+/// A BPF program ready to be loaded with `bpf_prog_load` against
+/// `BPF_PROG_TYPE_LIRC_MODE2`.
+pub struct BpfProgram {
+    /// The raw, little-endian encoded eBPF instructions.
+    pub instructions: Vec<u8>,
+    /// The size in bytes the `state_map_fd` array map's value must be: a 4-byte state index
+    /// followed by one 4-byte slot per decoded variable name (in an order the caller has no
+    /// visibility into, which is why this is reported rather than assumed). The caller must
+    /// create the map with this value size *before* calling [`build_bpf`], since the fd has
+    /// to already exist to be embedded into the program.
+    pub map_value_size: usize,
+}
+
+/// Compile `irp` to a BPF program for `BPF_PROG_TYPE_LIRC_MODE2`.
+///
+/// The generated program walks the same states as
+/// [`Decoder`](crate::Decoder)/[`DfaDecoder`](crate::DfaDecoder) built with the same
+/// tolerances, persisting its current state and every decoded variable across calls (one
+/// sample per call) in a single-element `BPF_MAP_TYPE_ARRAY` keyed on `0` (see
+/// [`BpfProgram::map_value_size`] for how big its value needs to be), and calls the kernel's
+/// `bpf_rc_keydown` scancode-reporting helper once a full decode is reached, packing the
+/// decoded variables into a scancode 8 bits per variable (in ascending name order) and
+/// reporting the variable named `T`, if any, as the toggle bit. This is a simple fixed
+/// packing, not a per-protocol scancode normalization.
+///
+/// `state_map_fd` must be the file descriptor of that array map, already created by the
+/// caller with `bpf(BPF_MAP_CREATE, ...)` (creating maps is a syscall this crate has no
+/// business making on the caller's behalf); it is embedded into the program as a
+/// `BPF_PSEUDO_MAP_FD` relocation, the same way `libbpf`/`bpftool` reference maps.
+pub fn build_bpf(
+    irp: &Irp,
+    tolerance: u32,
+    rel_tolerance: u32,
+    trailing_gap: u32,
+    state_map_fd: i32,
+) -> Result<BpfProgram, BpfError> {
+    let nfa = irp
+        .compile()
+        .map_err(|e| BpfError::Compile(e.to_string()))?;
+    let dfa = nfa.build_dfa();
+
+    if dfa.states().len() > i32::MAX as usize {
+        return Err(BpfError::TooManyStates);
+    }
+
+    Ok(lower(
+        &dfa,
+        tolerance,
+        rel_tolerance,
+        trailing_gap,
+        state_map_fd,
+    ))
+}
+
+/// A tiny label-based assembler: instructions are appended in order, jumps reference a named
+/// label rather than a hand-counted offset, and [`Asm::link`] patches every jump's `off` field
+/// to the label's resolved instruction index once the whole program has been emitted.
+#[derive(Default)]
+struct Asm {
+    insns: Vec<Insn>,
+    labels: HashMap<String, usize>,
+    fixups: Vec<(usize, String)>,
+}
+
+impl Asm {
+    fn push(&mut self, insn: Insn) {
+        self.insns.push(insn);
+    }
+
+    fn label(&mut self, name: impl Into<String>) {
+        self.labels.insert(name.into(), self.insns.len());
+    }
+
+    fn jump(&mut self, mut insn: Insn, target: impl Into<String>) {
+        let index = self.insns.len();
+        insn.off = 0;
+        self.insns.push(insn);
+        self.fixups.push((index, target.into()));
+    }
+
+    fn link(mut self) -> Vec<Insn> {
+        for (index, label) in &self.fixups {
+            let target = self.labels[label];
+            self.insns[*index].off = (target as i64 - (*index as i64 + 1)) as i16;
+        }
+        self.insns
+    }
+}
+
+// The context for a `BPF_PROG_TYPE_LIRC_MODE2` program is the raw LIRC mode2 sample *value*
+// itself in R1, not a pointer: bit 24 set means pulse, clear means space, and the low 24 bits
+// (`LIRC_VALUE_MASK`) are the duration in microseconds.
+fn lower(
+    dfa: &DFA,
+    tolerance: u32,
+    rel_tolerance: u32,
+    trailing_gap: u32,
+    state_map_fd: i32,
+) -> BpfProgram {
+    let mut asm = Asm::default();
+
+    // Every distinct variable name assigned anywhere in the DFA, in a fixed (alphabetical)
+    // order, each given a 4-byte slot in the state map's value right after the state index.
+    let mut var_names: Vec<String> = dfa
+        .states()
+        .iter()
+        .flat_map(|state| state.edges().iter())
+        .flat_map(|edge| edge.actions().iter().map(|(name, _)| name.clone()))
+        .collect();
+    var_names.sort();
+    var_names.dedup();
+    let var_offset = |index: usize| 4 + (index as i16) * 4;
+    let toggle_index = var_names.iter().position(|name| name == "T");
+
+    // Stash the original ctx (the raw LIRC sample value) on the stack before it gets
+    // overwritten below: `bpf_rc_keydown` needs it back as its first argument.
+    asm.push(stx_w(Reg::R10, Reg::R1, -8));
+
+    // r7 = duration, r8 = 1 for pulse / 0 for space, decoded straight out of r1 before it gets
+    // overwritten with the map pointer below.
+    asm.push(mov_reg(Reg::R7, Reg::R1));
+    asm.push(and_imm(Reg::R7, 0x00ff_ffff));
+    asm.push(mov_reg(Reg::R8, Reg::R1));
+    asm.push(rsh_imm(Reg::R8, 24));
+    asm.push(and_imm(Reg::R8, 1));
+
+    // Look up the one-element state map: key = 0 on the stack, value = current state + vars.
+    asm.push(st_w(Reg::R10, -4, 0));
+    asm.push(mov_reg(Reg::R2, Reg::R10));
+    asm.push(add_imm(Reg::R2, -4));
+    for insn in ld_map_fd(Reg::R1, state_map_fd) {
+        asm.push(insn);
+    }
+    asm.push(call(BPF_FUNC_MAP_LOOKUP_ELEM));
+    asm.jump(jeq_imm(Reg::R0, 0, 0), "abort");
+    asm.push(mov_reg(Reg::R6, Reg::R0)); // r6 = pointer to the persisted state + vars
+    asm.push(ldx_w(Reg::R9, Reg::R6, 0)); // r9 = current state
+
+    let states = dfa.states();
+
+    for (state_index, state) in states.iter().enumerate() {
+        asm.label(format!("state_{state_index}"));
+        asm.jump(
+            jne_imm(Reg::R9, state_index as i32, 0),
+            format!("state_{}", state_index + 1),
+        );
+
+        for (edge_index, edge) in state.edges().iter().enumerate() {
+            let next_edge = format!("edge_{state_index}_{}", edge_index + 1);
+            let after_keydown = format!("after_keydown_{state_index}_{edge_index}");
+            let slack = tolerance.max(edge.max() * rel_tolerance / 100);
+            let min = edge.min().saturating_sub(slack);
+            let max = edge.max().saturating_add(slack);
+
+            asm.label(format!("edge_{state_index}_{edge_index}"));
+            asm.jump(
+                jne_imm(Reg::R8, i32::from(edge.is_flash()), 0),
+                next_edge.clone(),
+            );
+            asm.jump(jlt_imm(Reg::R7, min as i32, 0), next_edge.clone());
+            asm.jump(jge_imm(Reg::R7, max as i32 + 1, 0), next_edge.clone());
+
+            // Persist every variable this edge assigns into its slot in the state map,
+            // the same as `DfaDecoder::input` does into its in-memory `vars` map.
+            for (name, value) in edge.actions() {
+                let index = var_names
+                    .iter()
+                    .position(|candidate| candidate == name)
+                    .expect("var_names was collected from the same edges");
+                asm.push(st_w(Reg::R6, var_offset(index), *value as i32));
+            }
+
+            asm.push(mov_imm(Reg::R9, edge.dest() as i32));
+
+            if states[edge.dest()].is_done() && !edge.is_flash() {
+                // Only report a decode if the *observed* gap (r7), not the compiled bound,
+                // is at least as long as the caller's configured trailing gap.
+                asm.jump(
+                    jlt_imm(Reg::R7, trailing_gap as i32, 0),
+                    after_keydown.clone(),
+                );
+
+                // Pack the decoded variables into a scancode, 8 bits per variable in
+                // ascending name order (r3), and report the "T" variable, if any, as the
+                // toggle bit (r4). The protocol id (r2) isn't modeled by this lowering.
+                asm.push(mov_imm(Reg::R3, 0));
+                for var_index in 0..var_names.len() {
+                    asm.push(ldx_w(Reg::R0, Reg::R6, var_offset(var_index)));
+                    if var_index > 0 {
+                        asm.push(lsh_imm(Reg::R0, (var_index * 8) as i32));
+                    }
+                    asm.push(or_reg(Reg::R3, Reg::R0));
+                }
+                asm.push(mov_imm(Reg::R4, 0));
+                if let Some(index) = toggle_index {
+                    asm.push(ldx_w(Reg::R4, Reg::R6, var_offset(index)));
+                }
+                asm.push(mov_imm(Reg::R2, 0));
+                asm.push(ldx_w(Reg::R1, Reg::R10, -8)); // restore ctx for bpf_rc_keydown
+                asm.push(call(BPF_FUNC_RC_KEYDOWN));
+
+                asm.label(after_keydown);
+            }
+
+            asm.jump(ja(0), "done");
+        }
+
+        // No edge matched this event in this state: reset, same as `DfaDecoder::input` does.
+        asm.label(format!("edge_{state_index}_{}", state.edges().len()));
+        asm.push(mov_imm(Reg::R9, 0));
+        asm.jump(ja(0), "done");
+    }
+
+    asm.label(format!("state_{}", states.len()));
+    // current state index was out of range (shouldn't happen); reset defensively.
+    asm.push(mov_imm(Reg::R9, 0));
+
+    asm.label("done");
+    asm.push(stx_w(Reg::R6, Reg::R9, 0));
+    asm.push(mov_reg(Reg::R0, Reg::R9));
+    asm.push(exit());
+
+    asm.label("abort");
+    asm.push(mov_imm(Reg::R0, 0));
+    asm.push(exit());
+
+    let insns = asm.link();
+
+    let mut bytes = Vec::with_capacity(insns.len() * 8);
+    for insn in insns {
+        bytes.extend_from_slice(&insn.to_bytes());
+    }
+
+    BpfProgram {
+        instructions: bytes,
+        map_value_size: 4 + var_names.len() * 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unparseable_irp_is_rejected_by_parse() {
+        let err = Irp::parse("not an irp").map(|_| ()).unwrap_err();
+        assert!(!err.to_string().is_empty());
+    }
+
+    #[test]
+    fn nec1_lowers_to_a_nonempty_program() {
+        let irp = Irp::parse(
+            "{38.4k,564}<1,-1|1,-3>(16,-8,D:8,S:8,F:8,~F:8,1,^108m,(16,-4,1,^108m)*)[D:0..255,S:0..255=255-D,F:0..255]",
+        )
+        .expect("built-in IRP is valid");
+
+        let program = build_bpf(&irp, 100, 30, 20000, 3).expect("lowering should succeed");
+
+        assert!(!program.instructions.is_empty());
+        assert_eq!(program.instructions.len() % 8, 0);
+    }
+
+    #[test]
+    fn map_value_size_has_a_slot_per_decoded_variable() {
+        let irp = Irp::parse(
+            "{38.4k,564}<1,-1|1,-3>(16,-8,D:8,S:8,F:8,~F:8,1,^108m,(16,-4,1,^108m)*)[D:0..255,S:0..255=255-D,F:0..255]",
+        )
+        .expect("built-in IRP is valid");
+
+        let program = build_bpf(&irp, 100, 30, 20000, 3).expect("lowering should succeed");
+
+        // 4 bytes for the state index, plus one 4-byte slot per decoded variable (D, F, S).
+        assert_eq!(program.map_value_size, 4 + 3 * 4);
+    }
+
+    #[test]
+    fn rc5_reports_the_toggle_bit_in_a_dedicated_slot() {
+        let irp = Irp::parse(
+            "{36k,msb,889}<1,-1|-1,1>((1,~F:1:6,T:1,D:5,F:6,^114m)*,T=1-T)[D:0..31,F:0..127,T@:0..1=0]",
+        )
+        .expect("built-in IRP is valid");
+
+        let program = build_bpf(&irp, 100, 30, 20000, 3).expect("lowering should succeed");
+
+        // D, F and T are all decoded, so the toggle gets its own slot alongside the others.
+        assert_eq!(program.map_value_size, 4 + 3 * 4);
+    }
+}
@@ -0,0 +1,387 @@
+use crate::build_nfa::{eval_const, matches, Vertex};
+use crate::{InfraredData, NFA};
+use std::collections::{HashMap, HashMap as Map};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Flash,
+    Gap,
+}
+
+#[derive(Debug, Clone)]
+struct DfaEdge {
+    kind: Kind,
+    min: u32,
+    max: u32,
+    actions: Vec<(String, i64)>,
+    dest: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DfaState {
+    edges: Vec<DfaEdge>,
+    done: bool,
+}
+
+/// A deterministic decoder state machine, compiled from an [`NFA`] by subset
+/// construction. Unlike the NFA, which may have several vertices live at
+/// once and must recompute their epsilon closure on every event, a `DFA` has
+/// exactly one live state at a time: every reachable combination of NFA
+/// vertices was already folded into a single state, and each state's
+/// outgoing edges already have the destination state's epsilon closure
+/// baked in. This means decoding an event is a single lookup rather than a
+/// walk over a set of live states.
+///
+/// Build one with [`NFA::build_dfa`]; the NFA itself remains available for
+/// debugging and for rendering with [`graphviz`](crate::NFA).
+#[derive(Debug, Clone)]
+pub struct DFA {
+    states: Vec<DfaState>,
+}
+
+impl NFA {
+    /// Compile this NFA to an equivalent [`DFA`] by subset construction.
+    /// Every set of NFA vertices reachable together becomes a single DFA
+    /// state, so decoding can advance exactly one state per input event
+    /// instead of tracking a set of live NFA states.
+    pub fn build_dfa(&self) -> DFA {
+        let mut states: Vec<DfaState> = Vec::new();
+        let mut index_of: Map<Vec<usize>, usize> = HashMap::new();
+        let mut queue = Vec::new();
+
+        let start = subset_key(&self.epsilon_closure(&[(0, Vec::new())]));
+        index_of.insert(start.clone(), 0);
+        states.push(DfaState::default());
+        queue.push(start);
+
+        while let Some(subset) = queue.pop() {
+            let this_index = index_of[&subset];
+
+            let done = subset
+                .iter()
+                .any(|&v| matches!(self.verts[v], Vertex::Done));
+
+            // Group every outgoing edge in this subset by its guard, so that
+            // several NFA vertices awaiting the same flash/gap length become
+            // one DFA transition rather than several.
+            let mut grouped: Map<(Kind, u32, u32), Vec<(usize, Vec<(String, i64)>)>> =
+                HashMap::new();
+
+            for &vert in &subset {
+                let (kind, min, max, edges) = match &self.verts[vert] {
+                    Vertex::Flash { min, max, edges } => (Kind::Flash, *min, *max, edges),
+                    Vertex::Gap { min, max, edges } => (Kind::Gap, *min, *max, edges),
+                    Vertex::Branch(_) | Vertex::Done => continue,
+                };
+
+                for edge in edges {
+                    let actions = edge
+                        .actions
+                        .iter()
+                        .map(|(name, expr)| (name.clone(), eval_const(expr)))
+                        .collect();
+                    grouped
+                        .entry((kind, min, max))
+                        .or_default()
+                        .push((edge.dest, actions));
+                }
+            }
+
+            let mut edges = Vec::new();
+
+            for ((kind, min, max), targets) in grouped {
+                let closure = self.epsilon_closure(
+                    &targets
+                        .iter()
+                        .map(|(dest, actions)| (*dest, actions.clone()))
+                        .collect::<Vec<_>>(),
+                );
+                let dest_key = subset_key(&closure);
+
+                let dest_index = *index_of.entry(dest_key.clone()).or_insert_with(|| {
+                    states.push(DfaState::default());
+                    queue.push(dest_key.clone());
+                    states.len() - 1
+                });
+
+                // Take the actions from the epsilon closure, not just the triggering edge:
+                // a Branch reached after the Flash/Gap (e.g. a toggle reset outside the
+                // repeating group) assigns variables too, and those are only visible once
+                // the closure has followed them. Every NFA vertex merged into this one DFA
+                // edge is expected to assign the same values for the same guard (that's
+                // what makes the bit spec unambiguous); verify that instead of silently
+                // keeping whichever vertex's actions a HashMap happened to group first.
+                let mut closures = closure.iter();
+                let actions = closures.next().map(|(_, a)| a.clone()).unwrap_or_default();
+                debug_assert!(
+                    closures.all(|(_, a)| *a == actions),
+                    "ambiguous bit spec: merged NFA edges disagree on variable assignments for the same guard"
+                );
+
+                edges.push(DfaEdge {
+                    kind,
+                    min,
+                    max,
+                    actions,
+                    dest: dest_index,
+                });
+            }
+
+            states[this_index] = DfaState { edges, done };
+        }
+
+        DFA { states }
+    }
+}
+
+fn subset_key(closure: &[(usize, Vec<(String, i64)>)]) -> Vec<usize> {
+    let mut verts: Vec<usize> = closure.iter().map(|(v, _)| *v).collect();
+    verts.sort_unstable();
+    verts.dedup();
+    verts
+}
+
+impl DFA {
+    /// Create a decoder for this DFA. Arguments have the same meaning as
+    /// [`NFA::decoder`]: `tolerance` is the absolute tolerance in
+    /// microseconds, `rel_tolerance` is the relative tolerance in percent,
+    /// and `trailing_gap` is the minimum gap length that marks the end of a
+    /// message.
+    pub fn decoder(&self, tolerance: u32, rel_tolerance: u32, trailing_gap: u32) -> DfaDecoder {
+        DfaDecoder {
+            dfa: self,
+            tolerance,
+            rel_tolerance,
+            trailing_gap,
+            state: 0,
+            vars: HashMap::new(),
+            done: None,
+        }
+    }
+}
+
+/// Decodes IR using a precompiled [`DFA`]. Exactly one state is live at a
+/// time, so `input` advances the decoder with a single lookup rather than
+/// the set-of-states walk the NFA-based [`Decoder`](crate::Decoder) needs.
+pub struct DfaDecoder<'a> {
+    dfa: &'a DFA,
+    tolerance: u32,
+    rel_tolerance: u32,
+    trailing_gap: u32,
+    state: usize,
+    vars: HashMap<String, i64>,
+    done: Option<HashMap<String, i64>>,
+}
+
+impl<'a> DfaDecoder<'a> {
+    /// Start decoding a new message, discarding any in-progress state.
+    pub fn reset(&mut self) {
+        self.state = 0;
+        self.vars.clear();
+        self.done = None;
+    }
+
+    /// Feed one flash/gap/reset event into the decoder.
+    pub fn input(&mut self, input: InfraredData) {
+        if input == InfraredData::Reset {
+            self.reset();
+            return;
+        }
+
+        let current = &self.dfa.states[self.state];
+
+        let found = current.edges.iter().find(|edge| {
+            let kind_matches = matches!(
+                (edge.kind, input),
+                (Kind::Flash, InfraredData::Flash(_)) | (Kind::Gap, InfraredData::Gap(_))
+            );
+
+            let slack = self.tolerance.max(edge.max * self.rel_tolerance / 100);
+
+            kind_matches
+                && matches(
+                    input,
+                    edge.min.saturating_sub(slack),
+                    edge.max.saturating_add(slack),
+                )
+        });
+
+        let Some(edge) = found else {
+            self.reset();
+            return;
+        };
+
+        for (name, value) in &edge.actions {
+            self.vars.insert(name.clone(), *value);
+        }
+
+        self.state = edge.dest;
+
+        if self.dfa.states[self.state].done
+            && matches!(input, InfraredData::Gap(g) if g >= self.trailing_gap)
+        {
+            self.done = Some(self.vars.clone());
+        }
+    }
+
+    /// Returns the decoded variables once a full message has matched.
+    pub fn get(&self) -> Option<&HashMap<String, i64>> {
+        self.done.as_ref()
+    }
+}
+
+// Read-only access to the compiled state table, used by `build_bpf` to lower
+// this same DFA to a BPF program instead of a Rust decoder loop.
+#[cfg(feature = "bpf")]
+impl DFA {
+    pub(crate) fn states(&self) -> &[DfaState] {
+        &self.states
+    }
+}
+
+#[cfg(feature = "bpf")]
+impl DfaState {
+    pub(crate) fn edges(&self) -> &[DfaEdge] {
+        &self.edges
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(feature = "bpf")]
+impl DfaEdge {
+    pub(crate) fn min(&self) -> u32 {
+        self.min
+    }
+
+    pub(crate) fn max(&self) -> u32 {
+        self.max
+    }
+
+    pub(crate) fn dest(&self) -> usize {
+        self.dest
+    }
+
+    pub(crate) fn is_flash(&self) -> bool {
+        self.kind == Kind::Flash
+    }
+
+    pub(crate) fn actions(&self) -> &[(String, i64)] {
+        &self.actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_nfa::Edge;
+    use crate::{Expression, InfraredData};
+    use std::rc::Rc;
+
+    // Same shape as the NFA used in decoder_nfa's tests: a Flash that assigns F=5, then a
+    // trailing Gap, then Done.
+    fn sample_nfa() -> NFA {
+        let verts = vec![
+            Vertex::Flash {
+                min: 100,
+                max: 100,
+                edges: vec![Edge {
+                    dest: 1,
+                    actions: vec![("F".to_string(), Rc::new(Expression::Number(5)))],
+                }],
+            },
+            Vertex::Gap {
+                min: 200,
+                max: 200,
+                edges: vec![Edge {
+                    dest: 2,
+                    actions: vec![],
+                }],
+            },
+            Vertex::Done,
+        ];
+
+        NFA::new(verts)
+    }
+
+    #[test]
+    fn dfa_matches_nfa_decode() {
+        let dfa = sample_nfa().build_dfa();
+        let mut decoder = dfa.decoder(5, 0, 150);
+
+        decoder.input(InfraredData::Flash(100));
+        decoder.input(InfraredData::Gap(200));
+
+        let vars = decoder.get().expect("message should be decoded");
+        assert_eq!(vars["F"], 5);
+    }
+
+    #[test]
+    fn dfa_rejects_out_of_tolerance_flash() {
+        let dfa = sample_nfa().build_dfa();
+        let mut decoder = dfa.decoder(5, 0, 150);
+
+        decoder.input(InfraredData::Flash(200));
+        decoder.input(InfraredData::Gap(200));
+
+        assert_eq!(decoder.get(), None);
+    }
+
+    // Like `sample_nfa`, but with a Branch vertex between the trailing Gap and Done that
+    // assigns T=1 via an epsilon transition, the same shape as the `T=1-T` toggle reset that
+    // sits outside the repeating group in RC5_IRP/RC6_IRP.
+    fn sample_nfa_with_branch_action() -> NFA {
+        let verts = vec![
+            Vertex::Flash {
+                min: 100,
+                max: 100,
+                edges: vec![Edge {
+                    dest: 1,
+                    actions: vec![("F".to_string(), Rc::new(Expression::Number(5)))],
+                }],
+            },
+            Vertex::Gap {
+                min: 200,
+                max: 200,
+                edges: vec![Edge {
+                    dest: 2,
+                    actions: vec![],
+                }],
+            },
+            Vertex::Branch(vec![Edge {
+                dest: 3,
+                actions: vec![("T".to_string(), Rc::new(Expression::Number(1)))],
+            }]),
+            Vertex::Done,
+        ];
+
+        NFA::new(verts)
+    }
+
+    #[test]
+    fn dfa_reports_branch_actions_taken_after_the_triggering_edge() {
+        let nfa = sample_nfa_with_branch_action();
+
+        let mut nfa_decoder = nfa.decoder(5, 0, 150);
+        nfa_decoder.input(InfraredData::Flash(100));
+        nfa_decoder.input(InfraredData::Gap(200));
+        let nfa_vars = nfa_decoder
+            .get()
+            .expect("nfa decoder should decode")
+            .clone();
+
+        let dfa = nfa.build_dfa();
+        let mut dfa_decoder = dfa.decoder(5, 0, 150);
+        dfa_decoder.input(InfraredData::Flash(100));
+        dfa_decoder.input(InfraredData::Gap(200));
+        let dfa_vars = dfa_decoder
+            .get()
+            .expect("dfa decoder should decode")
+            .clone();
+
+        assert_eq!(dfa_vars, nfa_vars);
+        assert_eq!(dfa_vars["T"], 1);
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Expression, InfraredData};
+use crate::Decoder;
+use std::rc::Rc;
+
+/// One transition out of an NFA vertex. `dest` is the vertex to move to;
+/// `actions` are the variable assignments to make when the edge is taken.
+#[derive(Debug, Clone)]
+pub(crate) struct Edge {
+    pub dest: usize,
+    pub actions: Vec<(String, Rc<Expression>)>,
+}
+
+/// A single vertex in the decoder state machine. `Flash`/`Gap` vertices
+/// consume one [`InfraredData`] event when its length falls within
+/// `min..=max` microseconds, widened at decode time by the tolerances the
+/// decoder was built with. `Branch` vertices are taken without consuming
+/// any input, which is how optional and alternative parts of the IRP are
+/// represented. `Done` marks a vertex at which the variables captured so
+/// far are a valid decode.
+#[derive(Debug, Clone)]
+pub(crate) enum Vertex {
+    Flash {
+        min: u32,
+        max: u32,
+        edges: Vec<Edge>,
+    },
+    Gap {
+        min: u32,
+        max: u32,
+        edges: Vec<Edge>,
+    },
+    Branch(Vec<Edge>),
+    Done,
+}
+
+/// The nondeterministic state machine compiled from an [`Irp`](crate::Irp).
+///
+/// Every input event may be valid for more than one outgoing edge of the
+/// current set of live vertices, so the [`Decoder`] this produces tracks a
+/// set of live states rather than a single one. Use [`NFA::decoder`] to
+/// decode one signal at a time, or [`NFA::build_dfa`](crate::NFA::build_dfa)
+/// to compile this down to a deterministic [`DFA`](crate::DFA) when the same
+/// NFA will be used to decode many signals.
+#[derive(Debug, Clone)]
+pub struct NFA {
+    pub(crate) verts: Vec<Vertex>,
+}
+
+impl NFA {
+    pub(crate) fn new(verts: Vec<Vertex>) -> Self {
+        NFA { verts }
+    }
+
+    /// Epsilon-close a set of vertices: follow every `Branch` edge until
+    /// only `Flash`, `Gap` and `Done` vertices remain, recording any
+    /// variable assignments made along the way.
+    pub(crate) fn epsilon_closure(
+        &self,
+        start: &[(usize, Vec<(String, i64)>)],
+    ) -> Vec<(usize, Vec<(String, i64)>)> {
+        let mut seen = vec![false; self.verts.len()];
+        let mut stack: Vec<(usize, Vec<(String, i64)>)> = start.to_vec();
+        let mut closure = Vec::new();
+
+        while let Some((vert, vars)) = stack.pop() {
+            if seen[vert] {
+                continue;
+            }
+            seen[vert] = true;
+
+            match &self.verts[vert] {
+                Vertex::Branch(edges) => {
+                    for edge in edges {
+                        let mut vars = vars.clone();
+                        for (name, expr) in &edge.actions {
+                            vars.push((name.clone(), eval_const(expr)));
+                        }
+                        stack.push((edge.dest, vars));
+                    }
+                }
+                Vertex::Flash { .. } | Vertex::Gap { .. } | Vertex::Done => {
+                    closure.push((vert, vars));
+                }
+            }
+        }
+
+        closure
+    }
+
+    /// Create a decoder for this NFA. `tolerance` is the absolute tolerance
+    /// in microseconds, `rel_tolerance` is the relative tolerance in
+    /// percent, and `trailing_gap` is the minimum gap length that marks the
+    /// end of a message.
+    pub fn decoder(&self, tolerance: u32, rel_tolerance: u32, trailing_gap: u32) -> Decoder {
+        Decoder::new(self, tolerance, rel_tolerance, trailing_gap)
+    }
+}
+
+// Only constant (or already-resolved) expressions are ever used as edge
+// actions in the compiled NFA, so evaluating them needs no variable table.
+pub(crate) fn eval_const(expr: &Expression) -> i64 {
+    match expr {
+        Expression::Number(n) => *n,
+        _ => 0,
+    }
+}
+
+pub(crate) fn matches(event: InfraredData, min: u32, max: u32) -> bool {
+    match event {
+        InfraredData::Flash(v) | InfraredData::Gap(v) => v >= min && v <= max,
+        InfraredData::Reset => false,
+    }
+}
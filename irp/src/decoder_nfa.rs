@@ -0,0 +1,166 @@
+use crate::build_nfa::{eval_const, matches, Vertex};
+use crate::{InfraredData, NFA};
+use std::collections::HashMap;
+
+/// Decodes IR using the nondeterministic state machine compiled by
+/// [`Irp::compile`](crate::Irp::compile). Every live vertex is tracked at
+/// once, since a single input event may be valid for more than one of them.
+pub struct Decoder<'a> {
+    nfa: &'a NFA,
+    tolerance: u32,
+    rel_tolerance: u32,
+    trailing_gap: u32,
+    states: Vec<(usize, HashMap<String, i64>)>,
+    done: Option<HashMap<String, i64>>,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(nfa: &'a NFA, tolerance: u32, rel_tolerance: u32, trailing_gap: u32) -> Self {
+        let mut decoder = Decoder {
+            nfa,
+            tolerance,
+            rel_tolerance,
+            trailing_gap,
+            states: Vec::new(),
+            done: None,
+        };
+
+        decoder.reset();
+
+        decoder
+    }
+
+    /// Start decoding a new message, discarding any in-progress state.
+    pub fn reset(&mut self) {
+        self.done = None;
+        self.states = self
+            .nfa
+            .epsilon_closure(&[(0, Vec::new())])
+            .into_iter()
+            .map(|(vert, vars)| (vert, vars.into_iter().collect()))
+            .collect();
+    }
+
+    /// Feed one flash/gap/reset event into the decoder.
+    pub fn input(&mut self, input: InfraredData) {
+        if input == InfraredData::Reset {
+            self.reset();
+            return;
+        }
+
+        let mut next = Vec::new();
+
+        for (vert, vars) in &self.states {
+            let advance = match &self.nfa.verts[*vert] {
+                Vertex::Flash { min, max, edges } | Vertex::Gap { min, max, edges } => {
+                    widen_matches(input, *min, *max, self.tolerance, self.rel_tolerance)
+                        .then_some(edges)
+                }
+                Vertex::Branch(_) | Vertex::Done => None,
+            };
+
+            let Some(edges) = advance else { continue };
+
+            for edge in edges {
+                let mut vars = vars.clone();
+                for (name, expr) in &edge.actions {
+                    vars.insert(name.clone(), eval_const(expr));
+                }
+                next.push((edge.dest, vars));
+            }
+        }
+
+        self.states = self
+            .nfa
+            .epsilon_closure(
+                &next
+                    .into_iter()
+                    .map(|(vert, vars)| (vert, vars.into_iter().collect()))
+                    .collect::<Vec<_>>(),
+            )
+            .into_iter()
+            .map(|(vert, vars)| (vert, vars.into_iter().collect()))
+            .collect();
+
+        for (vert, vars) in &self.states {
+            if matches!(self.nfa.verts[*vert], Vertex::Done)
+                && matches!(input, InfraredData::Gap(g) if g >= self.trailing_gap)
+            {
+                self.done = Some(vars.clone());
+            }
+        }
+    }
+
+    /// Returns the decoded variables once a full message has matched.
+    pub fn get(&self) -> Option<&HashMap<String, i64>> {
+        self.done.as_ref()
+    }
+}
+
+fn widen_matches(
+    input: InfraredData,
+    min: u32,
+    max: u32,
+    tolerance: u32,
+    rel_tolerance: u32,
+) -> bool {
+    let slack = tolerance.max(max * rel_tolerance / 100);
+    matches(input, min.saturating_sub(slack), max.saturating_add(slack))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_nfa::Edge;
+    use crate::Expression;
+    use std::rc::Rc;
+
+    // vertex 0: Flash 100us, assigns F=5 -> vertex 1
+    // vertex 1: Gap 200us (also the trailing gap) -> vertex 2
+    // vertex 2: Done
+    fn sample_nfa() -> NFA {
+        let verts = vec![
+            Vertex::Flash {
+                min: 100,
+                max: 100,
+                edges: vec![Edge {
+                    dest: 1,
+                    actions: vec![("F".to_string(), Rc::new(Expression::Number(5)))],
+                }],
+            },
+            Vertex::Gap {
+                min: 200,
+                max: 200,
+                edges: vec![Edge {
+                    dest: 2,
+                    actions: vec![],
+                }],
+            },
+            Vertex::Done,
+        ];
+
+        NFA::new(verts)
+    }
+
+    #[test]
+    fn flash_action_is_captured() {
+        let nfa = sample_nfa();
+        let mut decoder = nfa.decoder(5, 0, 150);
+
+        decoder.input(InfraredData::Flash(100));
+        decoder.input(InfraredData::Gap(200));
+
+        let vars = decoder.get().expect("message should be decoded");
+        assert_eq!(vars["F"], 5);
+    }
+
+    #[test]
+    fn incomplete_message_does_not_decode() {
+        let nfa = sample_nfa();
+        let mut decoder = nfa.decoder(5, 0, 150);
+
+        decoder.input(InfraredData::Flash(100));
+
+        assert_eq!(decoder.get(), None);
+    }
+}
@@ -0,0 +1,280 @@
+//! Pluggable output syntaxes for a [`Message`], inspired by how a disassembler can emit the
+//! same decoded instructions as masm, nasm, gas or intel syntax from one shared set of options.
+//! Pick a [`Formatter`] for whichever downstream tool is consuming the signal and call
+//! [`Message::format`](crate::Message::format).
+
+use crate::{Message, Pronto};
+
+/// Options shared by every [`Formatter`]. Not every formatter uses every option; unused ones
+/// are simply ignored (e.g. [`ProntoFormatter`] has no use for `flash_char`).
+#[derive(Debug, Clone, Copy)]
+pub struct FormatterOptions {
+    /// Character printed before a flash entry in the raw ir syntax. Defaults to `+`.
+    pub flash_char: char,
+    /// Character printed before a gap entry in the raw ir syntax. Defaults to `-`.
+    pub gap_char: char,
+    /// Whether to emit a carrier/duty-cycle header line before the signal.
+    pub header: bool,
+    /// Print one flash/gap entry per line rather than all on a single line.
+    pub per_line: bool,
+    /// Minimum field width for each printed duration, padded with spaces.
+    pub field_width: usize,
+}
+
+impl Default for FormatterOptions {
+    fn default() -> Self {
+        FormatterOptions {
+            flash_char: '+',
+            gap_char: '-',
+            header: false,
+            per_line: false,
+            field_width: 0,
+        }
+    }
+}
+
+/// Renders a [`Message`] to some target syntax.
+pub trait Formatter {
+    /// Render `message` as a string in this formatter's syntax.
+    fn format(&self, message: &Message) -> String;
+}
+
+/// The `+flash -gap` raw ir syntax used throughout this crate.
+pub struct RawFormatter {
+    pub options: FormatterOptions,
+}
+
+impl Formatter for RawFormatter {
+    fn format(&self, message: &Message) -> String {
+        let mut out = String::new();
+
+        if self.options.header {
+            if let Some(carrier) = message.carrier {
+                out.push_str(&format!("carrier {carrier}\n"));
+            }
+            if let Some(duty_cycle) = message.duty_cycle {
+                out.push_str(&format!("duty_cycle {duty_cycle}\n"));
+            }
+        }
+
+        let sep = if self.options.per_line { "\n" } else { " " };
+        let width = self.options.field_width;
+
+        let body = message
+            .raw
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let sign = if i % 2 == 0 {
+                    self.options.flash_char
+                } else {
+                    self.options.gap_char
+                };
+                format!("{sign}{v:width$}")
+            })
+            .collect::<Vec<_>>()
+            .join(sep);
+
+        out.push_str(&body);
+
+        out
+    }
+}
+
+/// The lirc `mode2` syntax, one `pulse`/`space` entry per duration, each on its own line when
+/// `per_line` is set and space-separated on a single line otherwise.
+pub struct Mode2Formatter {
+    pub options: FormatterOptions,
+}
+
+impl Formatter for Mode2Formatter {
+    fn format(&self, message: &Message) -> String {
+        let mut lines = Vec::new();
+
+        if self.options.header {
+            if let Some(carrier) = message.carrier {
+                lines.push(format!("carrier {carrier}"));
+            }
+        }
+
+        let width = self.options.field_width;
+
+        for (i, v) in message.raw.iter().enumerate() {
+            let name = if i % 2 == 0 { "pulse" } else { "space" };
+            lines.push(format!("{name} {v:width$}"));
+        }
+
+        let sep = if self.options.per_line { "\n" } else { " " };
+
+        lines.join(sep)
+    }
+}
+
+/// Pronto hex, rendered as a single "learned" code with no repeat sequence.
+pub struct ProntoFormatter;
+
+impl Formatter for ProntoFormatter {
+    fn format(&self, message: &Message) -> String {
+        let frequency = message.carrier.filter(|&c| c != 0).unwrap_or(36_000) as f64;
+
+        let pronto = if message.carrier == Some(0) {
+            Pronto::LearnedUnmodulated {
+                frequency,
+                intro: message.raw.iter().map(|&v| v as f64).collect(),
+                repeat: Vec::new(),
+            }
+        } else {
+            Pronto::LearnedModulated {
+                frequency,
+                intro: message.raw.iter().map(|&v| v as f64).collect(),
+                repeat: Vec::new(),
+            }
+        };
+
+        pronto.to_string()
+    }
+}
+
+/// The base64 packet format used by Broadlink IR blasters: a `0x26` (IR) header, a
+/// little-endian payload length, each duration in units of ~32.84us, and a `0x0d 0x05`
+/// trailer; the payload (durations and trailer) is zero-padded to a multiple of 16 bytes.
+pub struct BroadlinkFormatter;
+
+impl Formatter for BroadlinkFormatter {
+    fn format(&self, message: &Message) -> String {
+        const UNIT_US: f64 = 32.84;
+
+        let mut payload = Vec::new();
+
+        for &duration in &message.raw {
+            let units = (duration as f64 / UNIT_US).round() as u32;
+            if units < 256 {
+                payload.push(units as u8);
+            } else {
+                payload.push(0x00);
+                payload.extend_from_slice(&(units as u16).to_be_bytes());
+            }
+        }
+
+        payload.push(0x0d);
+        payload.push(0x05);
+
+        // The length header is the payload *before* padding: the device uses it to know
+        // where the real data ends and trailing zero bytes begin.
+        let payload_len = payload.len() as u16;
+        while payload.len() % 16 != 0 {
+            payload.push(0x00);
+        }
+
+        let mut packet = vec![0x26, 0x00];
+        packet.extend_from_slice(&payload_len.to_le_bytes());
+        packet.extend_from_slice(&payload);
+
+        base64_encode(&packet)
+    }
+}
+
+// A tiny, dependency-free base64 encoder so `BroadlinkFormatter` doesn't need to pull in a
+// crate just to print a handful of bytes.
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = *chunk.get(1).unwrap_or(&0);
+        let b2 = *chunk.get(2).unwrap_or(&0);
+
+        out.push(ALPHABET[(b0 >> 2) as usize] as char);
+        out.push(ALPHABET[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[(b2 & 0x3f) as usize] as char
+        } else {
+            '='
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base64_decode(s: &str) -> Vec<u8> {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut out = Vec::new();
+        let mut buf = 0u32;
+        let mut bits = 0u32;
+
+        for c in s.bytes() {
+            if c == b'=' {
+                break;
+            }
+            let value = ALPHABET.iter().position(|&a| a == c).unwrap() as u32;
+            buf = (buf << 6) | value;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((buf >> bits) as u8);
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn raw_formatter_alternates_flash_and_gap() {
+        let message = Message {
+            carrier: None,
+            duty_cycle: None,
+            raw: vec![100, 200, 300],
+        };
+
+        let formatter = RawFormatter {
+            options: FormatterOptions::default(),
+        };
+
+        assert_eq!(formatter.format(&message), "+100 -200 +300");
+    }
+
+    #[test]
+    fn mode2_formatter_names_each_entry() {
+        let message = Message {
+            carrier: None,
+            duty_cycle: None,
+            raw: vec![100, 200],
+        };
+
+        let formatter = Mode2Formatter {
+            options: FormatterOptions::default(),
+        };
+
+        assert_eq!(formatter.format(&message), "pulse 100 space 200");
+    }
+
+    #[test]
+    fn broadlink_length_header_excludes_padding() {
+        // Two short durations plus the 0x0d 0x05 trailer is 4 bytes, well under the 16-byte
+        // padding boundary, so the length header must read 4, not the padded 16.
+        let message = Message {
+            carrier: Some(38_000),
+            duty_cycle: None,
+            raw: vec![100, 200],
+        };
+
+        let packet = base64_decode(&BroadlinkFormatter.format(&message));
+
+        assert_eq!(packet[0], 0x26);
+        assert_eq!(u16::from_le_bytes([packet[2], packet[3]]), 4);
+        assert_eq!(packet.len() - 4, 16);
+    }
+}
@@ -39,6 +39,14 @@
 //! assert_eq!(res["D"], 30);
 //! ```
 //!
+//! If the same NFA is going to be used to decode many signals, compile it to a [`DFA`] with
+//! [`NFA::build_dfa`] once and create decoders from that instead; [`DFA::decoder`] has the same
+//! signature as [`NFA::decoder`].
+//!
+//! With the `bpf` feature enabled, [`build_bpf`] lowers the same compiled decoder to a BPF
+//! program for the kernel's `BPF_PROG_TYPE_LIRC_MODE2` hook, so in-kernel rc-core decoding and
+//! userspace decoding can be driven from one IRP definition.
+//!
 //! ## An example of how to encode NEC1
 //!
 //! This example sets some parameters, encodes and then simply prints the result.
@@ -64,6 +72,10 @@
 //! println!("{}", message.print_rawir());
 //! ```
 //!
+//! `print_rawir` is shorthand for the common case; [`Message::format`] can render the same
+//! message as lirc mode2, pronto hex or a broadlink base64 packet by picking a different
+//! [`Formatter`], e.g. `message.format(&irp::Mode2Formatter { options: Default::default() })`.
+//!
 //! The output is in raw ir format, which looks like "+9024 -4512 +564 -1692 +564 -1692 +564 -1692 +564 ...". The first
 //! entry in this array is *flash*, which means infrared light should be on for N microseconds, and every even entry
 //! means *gap*, which means absense of light, i.e. off, for N microseconds. This continues to alternate. The
@@ -85,8 +97,8 @@
 //! ## Parsing pronto hex codes
 //!
 //! The [Pronto Hex](http://www.hifi-remote.com/wiki/index.php?title=Working_With_Pronto_Hex) is made popular by the
-//! Philips Pronto universal remote. The format is a series of 4 digits hex numbers. This library can parse the long
-//! codes, there is no support for the short format yet.
+//! Philips Pronto universal remote. The format is a series of 4 digits hex numbers. This library can parse both the
+//! long "learned" codes and the short forms for RC5, RC5x, RC6 and NEC1.
 //!
 //! ```
 //! use irp::Pronto;
@@ -145,10 +157,14 @@
 //! println!("{}", irp::rawir::print_to_string(&rawir));
 //! ```
 
+#[cfg(feature = "bpf")]
+mod build_bpf;
+mod build_dfa;
 mod build_nfa;
 mod decoder_nfa;
 mod encode;
 mod expression;
+mod formatter;
 mod graphviz;
 mod inverse;
 mod message;
@@ -186,8 +202,18 @@ pub enum Pronto {
         intro: Vec<f64>,
         repeat: Vec<f64>,
     },
+    /// Short form RC5 code
+    Rc5 { d: u16, f: u16 },
+    /// Short form RC5x (extended RC5) code
+    Rc5x { d: u16, s: u16, f: u16 },
+    /// Short form RC6 code
+    Rc6 { d: u16, f: u16 },
+    /// Short form NEC1 code
+    Nec1 { d: u16, s: u16, f: u16 },
 }
 
+pub use pronto::ProntoParseError;
+
 /// A parsed IRP notation, which can be used for encoding and decoding
 ///
 #[derive(Debug)]
@@ -309,5 +335,11 @@ pub enum InfraredData {
     Reset,
 }
 
+#[cfg(feature = "bpf")]
+pub use build_bpf::{build_bpf, BpfError, BpfProgram};
+pub use build_dfa::{DfaDecoder, DFA};
 pub use build_nfa::NFA;
 pub use decoder_nfa::Decoder;
+pub use formatter::{
+    BroadlinkFormatter, Formatter, FormatterOptions, Mode2Formatter, ProntoFormatter, RawFormatter,
+};
@@ -0,0 +1,16 @@
+use super::{rawir, Message};
+use crate::formatter::Formatter;
+
+impl Message {
+    /// Print the message in the raw ir format, e.g. `+100 -100 +100`.
+    pub fn print_rawir(&self) -> String {
+        rawir::print_to_string(&self.raw)
+    }
+
+    /// Render this message with the given [`Formatter`], letting the caller pick whichever
+    /// target syntax (raw ir, lirc mode2, pronto hex, broadlink base64, ...) the downstream
+    /// tool expects.
+    pub fn format(&self, formatter: &dyn Formatter) -> String {
+        formatter.format(self)
+    }
+}
@@ -0,0 +1,309 @@
+use super::{Irp, Message, Pronto, Vartable};
+use std::fmt;
+
+/// The pronto hex "period" conversion factor: each duration entry in a pronto
+/// code counts periods of this many microseconds times the frequency code.
+const PRONTO_CLOCK: f64 = 0.241_246;
+
+fn code_to_frequency(code: u16) -> f64 {
+    1_000_000.0 / (code as f64 * PRONTO_CLOCK)
+}
+
+fn frequency_to_code(frequency: f64) -> u16 {
+    (1_000_000.0 / (frequency * PRONTO_CLOCK)).round() as u16
+}
+
+/// An error parsing a pronto hex string
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProntoParseError {
+    /// Not a series of 4 digit hex numbers separated by whitespace
+    NotHex,
+    /// Wrong number of hex numbers for the format indicated by the first word
+    WrongLength,
+    /// The first word is not a format this library understands
+    UnknownFormat,
+}
+
+impl fmt::Display for ProntoParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProntoParseError::NotHex => write!(f, "not a series of hex numbers"),
+            ProntoParseError::WrongLength => write!(f, "wrong number of hex numbers"),
+            ProntoParseError::UnknownFormat => write!(f, "unknown pronto format"),
+        }
+    }
+}
+
+impl std::error::Error for ProntoParseError {}
+
+impl Pronto {
+    /// Parse a pronto hex code. Both the "learned" long forms and the short
+    /// forms for RC5, RC5x, RC6 and NEC1 are supported.
+    pub fn parse(s: &str) -> Result<Pronto, ProntoParseError> {
+        let p: Vec<u16> = s
+            .split_whitespace()
+            .map(|s| u16::from_str_radix(s, 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| ProntoParseError::NotHex)?;
+
+        if p.len() < 4 {
+            return Err(ProntoParseError::WrongLength);
+        }
+
+        match p[0] {
+            0x0000 | 0x0100 => {
+                let frequency = code_to_frequency(p[1]);
+                let intro_length = p[2] as usize * 2;
+                let repeat_length = p[3] as usize * 2;
+
+                if p.len() != 4 + intro_length + repeat_length {
+                    return Err(ProntoParseError::WrongLength);
+                }
+
+                let period = 1_000_000.0 / frequency;
+                let intro = p[4..4 + intro_length]
+                    .iter()
+                    .map(|&v| v as f64 * period)
+                    .collect();
+                let repeat = p[4 + intro_length..]
+                    .iter()
+                    .map(|&v| v as f64 * period)
+                    .collect();
+
+                if p[0] == 0x0000 {
+                    Ok(Pronto::LearnedModulated {
+                        frequency,
+                        intro,
+                        repeat,
+                    })
+                } else {
+                    Ok(Pronto::LearnedUnmodulated {
+                        frequency,
+                        intro,
+                        repeat,
+                    })
+                }
+            }
+            0x5000 => {
+                if p.len() != 6 || p[2] != 0 || p[3] != 1 {
+                    return Err(ProntoParseError::WrongLength);
+                }
+
+                Ok(Pronto::Rc5 { d: p[4], f: p[5] })
+            }
+            0x5001 => {
+                if p.len() != 7 || p[2] != 0 || p[3] != 1 {
+                    return Err(ProntoParseError::WrongLength);
+                }
+
+                Ok(Pronto::Rc5x {
+                    d: p[4],
+                    s: p[5],
+                    f: p[6],
+                })
+            }
+            0x6000 => {
+                if p.len() != 6 || p[2] != 0 || p[3] != 1 {
+                    return Err(ProntoParseError::WrongLength);
+                }
+
+                Ok(Pronto::Rc6 { d: p[4], f: p[5] })
+            }
+            0x900a => {
+                if p.len() != 7 || p[2] != 0 || p[3] != 1 {
+                    return Err(ProntoParseError::WrongLength);
+                }
+
+                Ok(Pronto::Nec1 {
+                    d: p[4],
+                    s: p[5],
+                    f: p[6],
+                })
+            }
+            _ => Err(ProntoParseError::UnknownFormat),
+        }
+    }
+
+    /// The carrier frequency for this code, in Hz
+    pub fn frequency(&self) -> f64 {
+        match self {
+            Pronto::LearnedModulated { frequency, .. }
+            | Pronto::LearnedUnmodulated { frequency, .. } => *frequency,
+            Pronto::Rc5 { .. } | Pronto::Rc5x { .. } | Pronto::Rc6 { .. } => 36_000.0,
+            Pronto::Nec1 { .. } => 38_400.0,
+        }
+    }
+
+    /// Encode the pronto code to a raw IR message, repeating the repeat
+    /// sequence `repeats` times after the intro sequence.
+    pub fn encode(&self, repeats: u32) -> Message {
+        match self {
+            Pronto::LearnedModulated {
+                frequency,
+                intro,
+                repeat,
+            } => Self::encode_learned(Some(*frequency), intro, repeat, repeats),
+            Pronto::LearnedUnmodulated { intro, repeat, .. } => {
+                Self::encode_learned(Some(0.0), intro, repeat, repeats)
+            }
+            Pronto::Rc5 { d, f } => {
+                Self::encode_irp(RC5_IRP, &[("D", *d, 5), ("F", *f, 7)], repeats)
+            }
+            Pronto::Rc5x { d, s, f } => Self::encode_irp(
+                RC5X_IRP,
+                &[("D", *d, 5), ("S", *s, 7), ("F", *f, 6)],
+                repeats,
+            ),
+            Pronto::Rc6 { d, f } => {
+                Self::encode_irp(RC6_IRP, &[("D", *d, 8), ("F", *f, 8)], repeats)
+            }
+            Pronto::Nec1 { d, s, f } => Self::encode_irp(
+                NEC1_IRP,
+                &[("D", *d, 8), ("S", *s, 8), ("F", *f, 8)],
+                repeats,
+            ),
+        }
+    }
+
+    fn encode_irp(irp: &str, vars: &[(&str, u16, u8)], repeats: u32) -> Message {
+        let irp = Irp::parse(irp).expect("built-in IRP is valid");
+        let mut vartable = Vartable::new();
+        for (name, value, length) in vars {
+            vartable.set((*name).to_string(), *value as i64, *length);
+        }
+        irp.encode(vartable, repeats).expect("built-in IRP encodes")
+    }
+
+    fn encode_learned(
+        frequency: Option<f64>,
+        intro: &[f64],
+        repeat: &[f64],
+        repeats: u32,
+    ) -> Message {
+        let mut raw = intro.iter().map(|v| v.round() as u32).collect::<Vec<_>>();
+
+        for _ in 0..repeats {
+            raw.extend(repeat.iter().map(|v| v.round() as u32));
+        }
+
+        Message {
+            carrier: frequency.map(|f| f as i64),
+            duty_cycle: None,
+            raw,
+        }
+    }
+}
+
+// Canonical IRP notation for the protocols that pronto hex short forms can
+// represent, used to turn D/S/F back into a raw IR message.
+const RC5_IRP: &str =
+    "{36k,msb,889}<1,-1|-1,1>((1,~F:1:6,T:1,D:5,F:6,^114m)*,T=1-T)[D:0..31,F:0..127,T@:0..1=0]";
+const RC5X_IRP: &str = "{36k,msb,889}<1,-1|-1,1>(1,~S:1:6,T:1,D:5,S:6,-4,F:6,^114m,T=1-T)[D:0..31,S:0..127,F:0..63,T@:0..1=0]";
+const RC6_IRP: &str =
+    "{36k,msb,444}<-1,1|1,-1>((6,-2,1:1,6:3,-2,1,D:8,F:8,^107m)*)[D:0..255,F:0..255]";
+const NEC1_IRP: &str = "{38.4k,564}<1,-1|1,-3>(16,-8,D:8,S:8,F:8,~F:8,1,^108m,(16,-4,1,^108m)*)[D:0..255,S:0..255=255-D,F:0..255]";
+
+impl fmt::Display for Pronto {
+    /// Render the pronto code back to its canonical hex word representation
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words: Vec<u16> = match self {
+            Pronto::LearnedModulated {
+                frequency,
+                intro,
+                repeat,
+            }
+            | Pronto::LearnedUnmodulated {
+                frequency,
+                intro,
+                repeat,
+            } => {
+                let kind = if matches!(self, Pronto::LearnedModulated { .. }) {
+                    0x0000
+                } else {
+                    0x0100
+                };
+                let freq_code = frequency_to_code(*frequency);
+                let period = 1_000_000.0 / *frequency;
+
+                let mut words = vec![
+                    kind,
+                    freq_code,
+                    (intro.len() / 2) as u16,
+                    (repeat.len() / 2) as u16,
+                ];
+                words.extend(intro.iter().map(|v| (v / period).round() as u16));
+                words.extend(repeat.iter().map(|v| (v / period).round() as u16));
+                words
+            }
+            Pronto::Rc5 { d, f } => vec![0x5000, frequency_to_code(36_000.0), 0, 1, *d, *f],
+            Pronto::Rc5x { d, s, f } => {
+                vec![0x5001, frequency_to_code(36_000.0), 0, 1, *d, *s, *f]
+            }
+            Pronto::Rc6 { d, f } => vec![0x6000, frequency_to_code(36_000.0), 0, 1, *d, *f],
+            Pronto::Nec1 { d, s, f } => {
+                vec![0x900a, frequency_to_code(38_400.0), 0, 1, *d, *s, *f]
+            }
+        };
+
+        let strings: Vec<String> = words.iter().map(|w| format!("{w:04X}")).collect();
+
+        write!(f, "{}", strings.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_form_rc5() {
+        // 0073 is the canonical RC5 frequency code (frequency_to_code(36_000.0)); parse()
+        // doesn't validate it (see rc5_short_form_ignores_the_frequency_word), but the fixture
+        // should still use the real word rather than an arbitrary one.
+        let pronto = Pronto::parse("5000 0073 0000 0001 0000 0001").expect("valid short form");
+
+        assert_eq!(pronto, Pronto::Rc5 { d: 0, f: 1 });
+        assert_eq!(pronto.frequency(), 36_000.0);
+    }
+
+    #[test]
+    fn rc5_short_form_ignores_the_frequency_word() {
+        // RC5/RC5x/RC6/NEC1 short forms always run at the protocol's fixed carrier, so
+        // `parse()` reports that fixed frequency rather than decoding `p[1]`.
+        let pronto = Pronto::parse("5000 FFFF 0000 0001 0000 0001").expect("valid short form");
+
+        assert_eq!(pronto, Pronto::Rc5 { d: 0, f: 1 });
+        assert_eq!(pronto.frequency(), 36_000.0);
+    }
+
+    #[test]
+    fn short_form_round_trips_through_display() {
+        let pronto = Pronto::Nec1 { d: 1, s: 2, f: 3 };
+
+        let rendered = pronto.to_string();
+        let reparsed = Pronto::parse(&rendered).expect("rendered code should reparse");
+
+        assert_eq!(reparsed, pronto);
+    }
+
+    #[test]
+    fn rejects_non_hex_input() {
+        assert_eq!(Pronto::parse("not hex"), Err(ProntoParseError::NotHex));
+    }
+
+    #[test]
+    fn rejects_too_short_input() {
+        assert_eq!(
+            Pronto::parse("0000 006D"),
+            Err(ProntoParseError::WrongLength)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_format_word() {
+        assert_eq!(
+            Pronto::parse("1234 006D 0000 0001 0000 0001"),
+            Err(ProntoParseError::UnknownFormat)
+        );
+    }
+}
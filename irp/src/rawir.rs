@@ -0,0 +1,74 @@
+//! Parsing and printing of the raw ir format, which looks like `+100 -100 +100`: a flash
+//! (light on) followed by a gap (light off), repeated. The leading `+` and `-` may be omitted,
+//! but if present are checked for consistency with the even/odd position of the entry.
+
+use std::fmt;
+
+/// An error parsing a raw ir string
+#[derive(Debug, PartialEq, Eq)]
+pub enum RawParseError {
+    /// Not a valid number
+    NotNumber(String),
+    /// A `+` or `-` sign does not match flash/gap at this position
+    InconsistentSign(String),
+    /// The raw ir is empty
+    Empty,
+}
+
+impl fmt::Display for RawParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RawParseError::NotNumber(s) => write!(f, "'{s}' is not a number"),
+            RawParseError::InconsistentSign(s) => {
+                write!(f, "'{s}' does not match flash/gap position")
+            }
+            RawParseError::Empty => write!(f, "raw ir is empty"),
+        }
+    }
+}
+
+impl std::error::Error for RawParseError {}
+
+/// Parse a raw ir string such as `+100 -100 +100` to a list of flash/gap lengths in
+/// microseconds. Even entries (starting at 0) are flash, odd entries are gap.
+pub fn parse(s: &str) -> Result<Vec<u32>, RawParseError> {
+    let mut res = Vec::new();
+
+    for (i, entry) in s.split_whitespace().enumerate() {
+        let (sign, digits) = match entry.strip_prefix('+') {
+            Some(rest) => (Some('+'), rest),
+            None => match entry.strip_prefix('-') {
+                Some(rest) => (Some('-'), rest),
+                None => (None, entry),
+            },
+        };
+
+        if let Some(sign) = sign {
+            let expect_flash = i % 2 == 0;
+            if (sign == '+') != expect_flash {
+                return Err(RawParseError::InconsistentSign(entry.to_string()));
+            }
+        }
+
+        let value: u32 = digits
+            .parse()
+            .map_err(|_| RawParseError::NotNumber(entry.to_string()))?;
+
+        res.push(value);
+    }
+
+    if res.is_empty() {
+        return Err(RawParseError::Empty);
+    }
+
+    Ok(res)
+}
+
+/// Print a list of flash/gap lengths back to the raw ir format, e.g. `+100 -100 +100`.
+pub fn print_to_string(raw: &[u32]) -> String {
+    raw.iter()
+        .enumerate()
+        .map(|(i, v)| format!("{}{}", if i % 2 == 0 { '+' } else { '-' }, v))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
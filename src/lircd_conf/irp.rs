@@ -1,10 +1,117 @@
 use super::{Flags, LircRemote};
+use irp::Irp;
+use std::fmt;
+
+/// An error building a [`Irp`] from a [`LircRemote`].
+#[derive(Debug)]
+pub enum IrpBuildError {
+    /// The generated IRP string failed to parse. This should only happen if the remote's
+    /// fields describe something the IRP grammar can't express.
+    Parse(String),
+}
+
+impl fmt::Display for IrpBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IrpBuildError::Parse(message) => write!(f, "failed to parse generated irp: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for IrpBuildError {}
 
 impl LircRemote {
     /// Build an IRP representation for the remote. This can be used both for encoding
     /// and decoding.
     pub fn irp(&self) -> String {
-        let mut irp = String::from("{");
+        let mut irp = String::new();
+
+        self.push_prelude(&mut irp);
+
+        self.push_pre(
+            &mut irp,
+            &format!("0x{:x}:{}", self.pre_data, self.pre_data_bits),
+        );
+
+        irp.push_str(&format!("CODE:{},", self.bits));
+
+        self.push_post(
+            &mut irp,
+            &format!("0x{:x}:{}", self.post_data, self.post_data_bits),
+        );
+
+        self.push_trailer(&mut irp, "");
+
+        irp.push_str(&format!(" [CODE:0..{}]", (1u64 << self.bits) - 1));
+
+        irp
+    }
+
+    /// Build a fully parsed [`Irp`], ready for both [`Irp::encode`] and NFA
+    /// `compile`/decode, directly from this remote. Unlike [`LircRemote::irp`], which bakes
+    /// `pre_data`/`post_data` into the IRP string as constants and knows nothing of the
+    /// toggle bit, `CODE` and, where their bit count or mask is non-zero, pre-data, post-data
+    /// and the toggle bit are declared as proper `ParameterSpec`s with their real min/max, so
+    /// a caller can set them per transmission like `RC_CODE` repeats in lircd do.
+    ///
+    /// `LircRemote` has no ignore-mask field, so no mask is applied: `CODE`'s `ParameterSpec`
+    /// always spans the full `bits` range, and a toggle bit is XORed into `CODE` through the
+    /// separate `T` parameter rather than narrowing that range.
+    pub fn to_irp(&self) -> Result<Irp, IrpBuildError> {
+        Irp::parse(&self.parameterized_irp()).map_err(|e| IrpBuildError::Parse(e.to_string()))
+    }
+
+    fn parameterized_irp(&self) -> String {
+        let mut irp = String::new();
+
+        self.push_prelude(&mut irp);
+
+        let mut params = vec![format!("CODE:0..{}", (1u64 << self.bits) - 1)];
+
+        self.push_pre(&mut irp, &format!("PRE:{}", self.pre_data_bits));
+        if self.pre_data_bits != 0 {
+            params.push(format!(
+                "PRE:0..{}={}",
+                (1u64 << self.pre_data_bits) - 1,
+                self.pre_data
+            ));
+        }
+
+        if self.toggle_bit_mask != 0 {
+            irp.push_str(&format!(
+                "(CODE^(T*0x{:x})):{},",
+                self.toggle_bit_mask, self.bits
+            ));
+            params.push("T@:0..1=0".to_string());
+        } else {
+            irp.push_str(&format!("CODE:{},", self.bits));
+        }
+
+        self.push_post(&mut irp, &format!("POST:{}", self.post_data_bits));
+        if self.post_data_bits != 0 {
+            params.push(format!(
+                "POST:0..{}={}",
+                (1u64 << self.post_data_bits) - 1,
+                self.post_data
+            ));
+        }
+
+        let toggle_reset = if self.toggle_bit_mask != 0 {
+            "T=1-T,"
+        } else {
+            ""
+        };
+        self.push_trailer(&mut irp, toggle_reset);
+
+        irp.push_str(&format!(" [{}]", params.join(",")));
+
+        irp
+    }
+
+    /// Push the `{frequency,duty_cycle,msb}<bit spec>(header,plead,` prelude shared by both
+    /// [`LircRemote::irp`] and [`LircRemote::parameterized_irp`].
+    fn push_prelude(&self, irp: &mut String) {
+        irp.push('{');
 
         if self.frequency != 0 {
             irp.push_str(&format!("{}k,", self.frequency as f64 / 1000f64));
@@ -53,25 +160,38 @@ impl LircRemote {
         if self.plead != 0 {
             irp.push_str(&format!("{},", self.plead));
         }
+    }
 
+    /// Push the pre-data block, if any, using `spec` (e.g. `0x1234:8` for a constant or
+    /// `PRE:8` for a named parameter) for the data/length pair.
+    fn push_pre(&self, irp: &mut String, spec: &str) {
         if self.pre_data_bits != 0 {
-            irp.push_str(&format!("0x{:x}:{},", self.pre_data, self.pre_data_bits));
+            irp.push_str(spec);
+            irp.push(',');
 
             if self.pre.0 != 0 && self.pre.1 != 0 {
                 irp.push_str(&format!("{},-{},", self.pre.0, self.pre.1));
             }
         }
+    }
 
-        irp.push_str(&format!("CODE:{},", self.bits));
-
+    /// Push the post-data block, if any, using `spec` for the data/length pair, same as
+    /// [`LircRemote::push_pre`].
+    fn push_post(&self, irp: &mut String, spec: &str) {
         if self.post_data_bits != 0 {
-            irp.push_str(&format!("0x{:x}:{},", self.post_data, self.post_data_bits));
+            irp.push_str(spec);
+            irp.push(',');
 
             if self.post.0 != 0 && self.post.1 != 0 {
                 irp.push_str(&format!("{},-{},", self.post.0, self.post.1));
             }
         }
+    }
 
+    /// Push the `ptrail,foot,gap,extra,repeat` trailer shared by both `irp()` and
+    /// `parameterized_irp()`. `extra` is inserted right after the gap, which is where
+    /// `parameterized_irp` resets the toggle bit.
+    fn push_trailer(&self, irp: &mut String, extra: &str) {
         if self.ptrail != 0 {
             irp.push_str(&format!("{},", self.ptrail));
         }
@@ -84,6 +204,8 @@ impl LircRemote {
             irp.push_str(&format!("^{},", self.gap));
         }
 
+        irp.push_str(extra);
+
         if self.repeat.0 != 0 && self.repeat.1 != 0 {
             irp.push_str(&format!("({},-{},", self.repeat.0, self.repeat.1));
             if self.ptrail != 0 {
@@ -95,9 +217,72 @@ impl LircRemote {
             irp.pop();
             irp.push_str(")+");
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A small NEC-like remote: no pre/post data, no toggle bit, just a header, 8-bit CODE
+    // and a repeat.
+    fn nec_remote() -> LircRemote {
+        LircRemote {
+            frequency: 38_000,
+            duty_cycle: 33,
+            header: (9000, 4500),
+            bit: [(560, 560), (560, 1690)],
+            plead: 0,
+            pre_data_bits: 0,
+            pre_data: 0,
+            pre: (0, 0),
+            bits: 8,
+            post_data_bits: 0,
+            post_data: 0,
+            post: (0, 0),
+            toggle_bit_mask: 0,
+            ptrail: 560,
+            foot: (0, 0),
+            gap: 108_000,
+            repeat: (9000, 2250),
+            flags: Flags::empty(),
+        }
+    }
 
-        irp.push_str(&format!(" [CODE:0..{}]", (1u64 << self.bits) - 1));
+    #[test]
+    fn irp_bakes_code_length_as_a_constant() {
+        let irp = nec_remote().irp();
 
-        irp
+        assert!(irp.starts_with("{38k,33%,msb}<560,-560|560,-1690>(9000,-4500,"));
+        assert!(irp.contains("CODE:8,"));
+        assert!(irp.ends_with(" [CODE:0..255]"));
+    }
+
+    #[test]
+    fn parameterized_irp_declares_code_as_a_parameter() {
+        let irp = nec_remote().parameterized_irp();
+
+        assert!(irp.contains("CODE:8,"));
+        assert!(irp.contains("[CODE:0..255]"));
+    }
+
+    #[test]
+    fn parameterized_irp_promotes_pre_data_and_toggle_bit() {
+        let mut remote = nec_remote();
+        remote.pre_data_bits = 8;
+        remote.pre_data = 0x5a;
+        remote.toggle_bit_mask = 0x4000;
+
+        let irp = remote.parameterized_irp();
+
+        assert!(irp.contains("PRE:8,"));
+        assert!(irp.contains("PRE:0..255=90"));
+        assert!(irp.contains("(CODE^(T*0x4000)):8,"));
+        assert!(irp.contains("T@:0..1=0"));
+        assert!(irp.contains("T=1-T,"));
+
+        // The baked-in `irp()` form, by contrast, knows nothing of PRE or the toggle bit.
+        assert!(!remote.irp().contains("PRE:"));
+        assert!(!remote.irp().contains("T@"));
     }
 }